@@ -0,0 +1,133 @@
+//! Headless entry point: `timewarp run <file> [--language NAME] [--output PATH] [--turtle-svg PATH] [--stdlib]`.
+//!
+//! Runs a source file through `TimeWarpApp::execute_code` without opening the
+//! egui window, so programs can be driven from CI or a shell script.
+
+use crate::TimeWarpApp;
+use std::io::{self, BufRead, Write};
+
+/// Parsed `run` subcommand arguments.
+pub(crate) struct RunArgs {
+    file: String,
+    language: Option<String>,
+    output: Option<String>,
+    turtle_svg: Option<String>,
+    consult_stdlib: bool,
+}
+
+/// Parse `repl [--language NAME]` from the process arguments. Returns the
+/// requested language (if any), or `None` when the first argument isn't `repl`.
+pub(crate) fn parse_repl_args(args: &[String]) -> Option<Option<String>> {
+    if args.first().map(String::as_str) != Some("repl") {
+        return None;
+    }
+    let mut language = None;
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        if arg == "--language" {
+            language = rest.next().cloned();
+        }
+    }
+    Some(language)
+}
+
+/// Parse `run <file> [flags...]` from the process arguments (argv, without the
+/// binary name). Returns `None` when the first argument isn't `run`, so the
+/// caller can fall back to launching the GUI.
+pub(crate) fn parse_run_args(args: &[String]) -> Option<RunArgs> {
+    if args.first().map(String::as_str) != Some("run") {
+        return None;
+    }
+
+    let mut file = None;
+    let mut language = None;
+    let mut output = None;
+    let mut turtle_svg = None;
+    let mut consult_stdlib = false;
+
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--language" => language = rest.next().cloned(),
+            "--output" => output = rest.next().cloned(),
+            "--turtle-svg" => turtle_svg = rest.next().cloned(),
+            "--stdlib" | "--consult-stdlib" => consult_stdlib = true,
+            positional if file.is_none() => file = Some(positional.to_string()),
+            _ => {}
+        }
+    }
+
+    Some(RunArgs {
+        file: file.unwrap_or_default(),
+        language,
+        output,
+        turtle_svg,
+        consult_stdlib,
+    })
+}
+
+/// Run a source file in batch mode and write its captured output (and,
+/// optionally, turtle graphics as SVG) to stdout or the requested paths.
+/// `INPUT`/`readln`/PILOT `A:` prompts are answered by reading lines from stdin.
+pub(crate) fn run_headless(args: RunArgs) -> io::Result<()> {
+    let code = std::fs::read_to_string(&args.file)?;
+
+    let mut app = TimeWarpApp::default();
+    app.code = code;
+    if let Some(language) = args.language {
+        app.language = language;
+    }
+    app.consult_stdlib = args.consult_stdlib;
+
+    app.execute_code();
+    while app.waiting_for_input {
+        print!("{}", app.input_prompt);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        app.user_input = line.trim_end_matches(['\n', '\r']).to_string();
+        app.process_user_input();
+    }
+
+    match &args.output {
+        Some(path) => {
+            let mut report = app.output.clone();
+            if !app.diagnostics.is_empty() {
+                report.push_str("\n\n");
+                report.push_str(&crate::diagnostics::render_all(&app.diagnostics, &app.code, false));
+            }
+            std::fs::write(path, report)?;
+        }
+        None => {
+            println!("{}", app.output);
+            if !app.diagnostics.is_empty() {
+                println!();
+                println!("{}", crate::diagnostics::render_all(&app.diagnostics, &app.code, true));
+            }
+        }
+    }
+
+    if let Some(svg_path) = &args.turtle_svg {
+        std::fs::write(svg_path, render_turtle_svg(&app.turtle_commands))?;
+    }
+
+    Ok(())
+}
+
+/// Render recorded `line x1 y1 x2 y2` turtle commands as a minimal SVG document.
+fn render_turtle_svg(turtle_commands: &[String]) -> String {
+    let mut svg = String::from(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"400\" height=\"400\" viewBox=\"0 0 400 400\">\n",
+    );
+    for command in turtle_commands {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        if parts.len() == 5 && parts[0] == "line" {
+            svg.push_str(&format!(
+                "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\" stroke-width=\"2\" />\n",
+                parts[1], parts[2], parts[3], parts[4]
+            ));
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
@@ -0,0 +1,80 @@
+//! Structured diagnostics for the interpreters.
+//!
+//! Errors pushed into the output as bare strings like
+//! `"Unknown command: {}"` carry no location. `TimeWarpError` instead carries a
+//! source line and column span, so both the headless CLI (ANSI colors) and the
+//! egui Output tab (colored spans) can point straight at the offending code.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnknownCommand,
+    UnterminatedString,
+    UndefinedVariable,
+    TypeMismatch,
+    UnmatchedControlFlow,
+}
+
+impl ErrorKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ErrorKind::UnknownCommand => "unknown command",
+            ErrorKind::UnterminatedString => "unterminated string",
+            ErrorKind::UndefinedVariable => "undefined variable",
+            ErrorKind::TypeMismatch => "type mismatch",
+            ErrorKind::UnmatchedControlFlow => "unmatched NEXT/WEND/RETURN",
+        }
+    }
+}
+
+/// A single diagnostic, anchored to a 0-indexed source line and column span.
+#[derive(Debug, Clone)]
+pub struct TimeWarpError {
+    pub line: usize,
+    pub col_start: usize,
+    pub col_end: usize,
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl TimeWarpError {
+    pub fn new(line: usize, col_start: usize, col_end: usize, kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            col_start,
+            col_end,
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// The offending source line, reprinted with a caret underline beneath the span.
+    fn source_block(&self, source: &str) -> (String, String) {
+        let line_text = source.lines().nth(self.line).unwrap_or("").to_string();
+        let span_len = self.col_end.saturating_sub(self.col_start).max(1);
+        let caret = format!("{}{}", " ".repeat(self.col_start), "^".repeat(span_len));
+        (line_text, caret)
+    }
+
+    /// Render this diagnostic as a block: header, location, source line, caret
+    /// underline. Uses ANSI color codes when `color` is true (headless/terminal),
+    /// plain text otherwise (egui builds its own colored spans from the fields).
+    pub fn render(&self, source: &str, color: bool) -> String {
+        let (line_text, caret) = self.source_block(source);
+        let header = format!("error[{}]: {}", self.kind.label(), self.message);
+        let location = format!("  --> line {}", self.line + 1);
+        if color {
+            format!("\x1b[1;31m{}\x1b[0m\n{}\n  {}\n  \x1b[1;31m{}\x1b[0m", header, location, line_text, caret)
+        } else {
+            format!("{}\n{}\n  {}\n  {}", header, location, line_text, caret)
+        }
+    }
+}
+
+/// Render every diagnostic as one block, separated by a blank line.
+pub fn render_all(errors: &[TimeWarpError], source: &str, color: bool) -> String {
+    errors
+        .iter()
+        .map(|e| e.render(source, color))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
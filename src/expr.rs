@@ -0,0 +1,401 @@
+//! Shared expression subsystem used by TW BASIC and TW Pascal.
+//!
+//! `Value` replaces the old "everything is a string" variable store, and
+//! `Expr`/`parse` give every language a real precedence-climbing parser
+//! instead of ad-hoc string splitting.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// A runtime value. Numbers are always stored as `f64` so BASIC's integer
+/// and floating point literals share one representation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Str(String),
+    Bool(bool),
+}
+
+impl Value {
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Number(n) => *n != 0.0,
+            Value::Str(s) => !s.is_empty(),
+            Value::Bool(b) => *b,
+        }
+    }
+
+    /// How a value looks when PRINTed or written.
+    pub fn display(&self) -> String {
+        match self {
+            Value::Number(n) => {
+                if n.fract() == 0.0 && n.abs() < 1e15 {
+                    format!("{}", *n as i64)
+                } else {
+                    format!("{}", n)
+                }
+            }
+            Value::Str(s) => s.clone(),
+            Value::Bool(b) => (if *b { "TRUE" } else { "FALSE" }).to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display())
+    }
+}
+
+/// Variable store shared by TW BASIC and TW Pascal interpreters.
+pub type VarStore = HashMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Op(String),
+    LParen,
+    RParen,
+    Comma,
+    Eof,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        match c {
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '"' => {
+                let mut s = String::new();
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    s.push(chars[i]);
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(format!("Unterminated string: \"{}", s));
+                }
+                i += 1; // closing quote
+                tokens.push(Token::Str(s));
+            }
+            '+' | '-' | '*' | '/' => {
+                tokens.push(Token::Op(c.to_string()));
+                i += 1;
+            }
+            '=' => {
+                tokens.push(Token::Op("=".to_string()));
+                i += 1;
+            }
+            '<' => {
+                if i + 1 < chars.len() && (chars[i + 1] == '>' || chars[i + 1] == '=') {
+                    tokens.push(Token::Op(format!("<{}", chars[i + 1])));
+                    i += 2;
+                } else {
+                    tokens.push(Token::Op("<".to_string()));
+                    i += 1;
+                }
+            }
+            '>' => {
+                if i + 1 < chars.len() && chars[i + 1] == '=' {
+                    tokens.push(Token::Op(">=".to_string()));
+                    i += 2;
+                } else {
+                    tokens.push(Token::Op(">".to_string()));
+                    i += 1;
+                }
+            }
+            _ if c.is_ascii_digit() || c == '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let n = text.parse::<f64>().map_err(|_| format!("Invalid number: {}", text))?;
+                tokens.push(Token::Number(n));
+            }
+            _ if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let upper = text.to_uppercase();
+                match upper.as_str() {
+                    "AND" | "OR" | "MOD" => tokens.push(Token::Op(upper)),
+                    _ => tokens.push(Token::Ident(text)),
+                }
+            }
+            _ => return Err(format!("Unexpected character '{}'", c)),
+        }
+    }
+    tokens.push(Token::Eof);
+    Ok(tokens)
+}
+
+/// Parsed expression tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Str(String),
+    Var(String),
+    Neg(Box<Expr>),
+    Binary(String, Box<Expr>, Box<Expr>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn advance(&mut self) -> Token {
+        let t = self.tokens[self.pos].clone();
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        t
+    }
+
+    fn expect_rparen(&mut self) -> Result<(), String> {
+        if *self.peek() == Token::RParen {
+            self.advance();
+            Ok(())
+        } else {
+            Err("Expected ')'".to_string())
+        }
+    }
+
+    // Lowest binding power: comparisons and AND/OR, left-associative.
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_additive()?;
+        loop {
+            let op = match self.peek() {
+                Token::Op(o)
+                    if matches!(o.as_str(), "=" | "<>" | "<" | ">" | "<=" | ">=" | "AND" | "OR") =>
+                {
+                    o.clone()
+                }
+                _ => break,
+            };
+            self.advance();
+            let right = self.parse_additive()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Token::Op(o) if o == "+" || o == "-" => o.clone(),
+                _ => break,
+            };
+            self.advance();
+            let right = self.parse_multiplicative()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Token::Op(o) if o == "*" || o == "/" || o == "MOD" => o.clone(),
+                _ => break,
+            };
+            self.advance();
+            let right = self.parse_unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if let Token::Op(o) = self.peek() {
+            if o == "-" {
+                self.advance();
+                let inner = self.parse_unary()?;
+                return Ok(Expr::Neg(Box::new(inner)));
+            }
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr, String> {
+        match self.advance() {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Str(s) => Ok(Expr::Str(s)),
+            Token::Ident(name) => Ok(Expr::Var(name)),
+            Token::LParen => {
+                let inner = self.parse_comparison()?;
+                self.expect_rparen()?;
+                Ok(inner)
+            }
+            other => Err(format!("Unexpected token: {:?}", other)),
+        }
+    }
+}
+
+/// Parse an expression from source text.
+pub fn parse(src: &str) -> Result<Expr, String> {
+    let tokens = tokenize(src)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    parser.parse_comparison()
+}
+
+fn as_number(v: &Value) -> Result<f64, String> {
+    match v {
+        Value::Number(n) => Ok(*n),
+        Value::Bool(b) => Ok(if *b { 1.0 } else { 0.0 }),
+        Value::Str(s) => s.parse::<f64>().map_err(|_| format!("Expected a number, found \"{}\"", s)),
+    }
+}
+
+/// Evaluate a parsed expression against a variable store.
+pub fn eval(expr: &Expr, vars: &VarStore) -> Result<Value, String> {
+    match expr {
+        Expr::Number(n) => Ok(Value::Number(*n)),
+        Expr::Str(s) => Ok(Value::Str(interpolate(s, vars))),
+        Expr::Var(name) => vars
+            .get(name.as_str())
+            .cloned()
+            .ok_or_else(|| format!("Undefined variable: {}", name)),
+        Expr::Neg(inner) => Ok(Value::Number(-as_number(&eval(inner, vars)?)?)),
+        Expr::Binary(op, l, r) => {
+            let left = eval(l, vars)?;
+            if op == "AND" || op == "OR" {
+                let right = eval(r, vars)?;
+                let result = if op == "AND" {
+                    left.is_truthy() && right.is_truthy()
+                } else {
+                    left.is_truthy() || right.is_truthy()
+                };
+                return Ok(Value::Bool(result));
+            }
+            let right = eval(r, vars)?;
+            match op.as_str() {
+                "+" => match (&left, &right) {
+                    (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+                    _ => Ok(Value::Str(format!("{}{}", left.display(), right.display()))),
+                },
+                "-" => Ok(Value::Number(as_number(&left)? - as_number(&right)?)),
+                "*" => Ok(Value::Number(as_number(&left)? * as_number(&right)?)),
+                "/" => {
+                    let denom = as_number(&right)?;
+                    if denom == 0.0 {
+                        return Err("Division by zero".to_string());
+                    }
+                    Ok(Value::Number(as_number(&left)? / denom))
+                }
+                "MOD" => Ok(Value::Number(as_number(&left)? % as_number(&right)?)),
+                "=" => Ok(Value::Bool(values_equal(&left, &right))),
+                "<>" => Ok(Value::Bool(!values_equal(&left, &right))),
+                "<" | ">" | "<=" | ">=" => {
+                    let (a, b) = (as_number(&left)?, as_number(&right)?);
+                    let result = match op.as_str() {
+                        "<" => a < b,
+                        ">" => a > b,
+                        "<=" => a <= b,
+                        ">=" => a >= b,
+                        _ => unreachable!(),
+                    };
+                    Ok(Value::Bool(result))
+                }
+                other => Err(format!("Unknown operator: {}", other)),
+            }
+        }
+    }
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x == y,
+        (Value::Str(x), Value::Str(y)) => x == y,
+        (Value::Bool(x), Value::Bool(y)) => x == y,
+        _ => a.display() == b.display(),
+    }
+}
+
+/// Parse and evaluate a source expression in one step.
+pub fn eval_str(src: &str, vars: &VarStore) -> Result<Value, String> {
+    let expr = parse(src)?;
+    eval(&expr, vars)
+}
+
+/// Expand `$VAR` and `${VAR}` references in `text` to their current value,
+/// shell-style. `$$` escapes to a literal `$`. Unknown variables are left
+/// untouched so a typo doesn't silently vanish from the output.
+pub fn interpolate(text: &str, vars: &VarStore) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '$' {
+            result.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        if i + 1 < chars.len() && chars[i + 1] == '$' {
+            result.push('$');
+            i += 2;
+            continue;
+        }
+        if i + 1 < chars.len() && chars[i + 1] == '{' {
+            if let Some(end) = chars[i + 2..].iter().position(|&c| c == '}') {
+                let name: String = chars[i + 2..i + 2 + end].iter().collect();
+                match vars.get(&name) {
+                    Some(value) => result.push_str(&value.display()),
+                    None => result.push_str(&format!("${{{}}}", name)),
+                }
+                i += 2 + end + 1;
+                continue;
+            }
+        }
+        if i + 1 < chars.len() && (chars[i + 1].is_alphabetic() || chars[i + 1] == '_') {
+            let start = i + 1;
+            let mut end = start;
+            while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+                end += 1;
+            }
+            let name: String = chars[start..end].iter().collect();
+            match vars.get(&name) {
+                Some(value) => result.push_str(&value.display()),
+                None => {
+                    result.push('$');
+                    result.push_str(&name);
+                }
+            }
+            i = end;
+            continue;
+        }
+        result.push('$');
+        i += 1;
+    }
+    result
+}
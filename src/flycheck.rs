@@ -0,0 +1,150 @@
+//! Background, debounced syntax checking, flycheck-style: a worker thread
+//! receives the current buffer over a channel, waits out a debounce window so
+//! a burst of keystrokes triggers one check rather than one per keystroke,
+//! runs the language-appropriate syntax check, and sends back structured
+//! diagnostics for `TimeWarpApp::update` to drain without blocking the UI.
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+/// One flycheck finding, anchored to a 0-indexed source line (and, where the
+/// check can tell, a column) rather than the flat text blob `check_syntax`
+/// returns.
+#[derive(Debug, Clone)]
+pub(crate) struct Diagnostic {
+    pub(crate) line: usize,
+    pub(crate) col: usize,
+    pub(crate) message: String,
+    pub(crate) severity: Severity,
+}
+
+/// Runs `check` on a background thread and reports results back over a
+/// channel, debouncing rapid `request` calls.
+pub(crate) struct FlycheckWorker {
+    request_tx: Sender<(String, String)>,
+    result_rx: Receiver<Vec<Diagnostic>>,
+}
+
+impl FlycheckWorker {
+    pub(crate) fn spawn() -> Self {
+        let (request_tx, request_rx) = mpsc::channel::<(String, String)>();
+        let (result_tx, result_rx) = mpsc::channel::<Vec<Diagnostic>>();
+
+        thread::spawn(move || {
+            loop {
+                let Ok(mut latest) = request_rx.recv() else { break };
+                // Debounce: keep replacing `latest` with whatever arrives
+                // within the window, so only the final state gets checked.
+                loop {
+                    match request_rx.recv_timeout(DEBOUNCE) {
+                        Ok(next) => latest = next,
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                let (code, language) = latest;
+                if result_tx.send(check(&code, &language)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self { request_tx, result_rx }
+    }
+
+    /// Ask the worker to check `code`; supersedes any pending request still
+    /// waiting out its debounce window.
+    pub(crate) fn request(&self, code: String, language: String) {
+        let _ = self.request_tx.send((code, language));
+    }
+
+    /// Return the most recently completed check, if one has finished since
+    /// the last poll. Never blocks.
+    pub(crate) fn poll(&self) -> Option<Vec<Diagnostic>> {
+        let mut latest = None;
+        while let Ok(diagnostics) = self.result_rx.try_recv() {
+            latest = Some(diagnostics);
+        }
+        latest
+    }
+}
+
+/// Run the language-appropriate syntax check, structured as line-anchored
+/// diagnostics instead of a formatted text blob.
+pub(crate) fn check(code: &str, language: &str) -> Vec<Diagnostic> {
+    match language {
+        "TW BASIC" => check_basic(code),
+        "TW Pascal" => check_pascal(code),
+        "TW Prolog" => check_prolog(code),
+        _ => Vec::new(),
+    }
+}
+
+fn error(line: usize, message: impl Into<String>) -> Diagnostic {
+    Diagnostic { line, col: 0, message: message.into(), severity: Severity::Error }
+}
+
+fn check_basic(code: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    // Match how the interpreter itself decides dialect (see
+    // `execute_tw_basic`, which only sorts by line number when at least one
+    // is present): a program is only "line-numbered BASIC" if some non-blank
+    // line actually starts with a number. PILOT/Logo/modern-BASIC programs
+    // with no numbers at all are a different, equally valid dialect and
+    // shouldn't get a red squiggle on every single line for not having them.
+    let has_numbered_line = code
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .any(|line| line.chars().next().unwrap_or(' ').is_ascii_digit());
+
+    for (line_num, line) in code.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if has_numbered_line
+            && !line.chars().next().unwrap_or(' ').is_ascii_digit()
+            && !line.to_uppercase().starts_with("REM")
+        {
+            diagnostics.push(error(line_num, "BASIC programs should start with line numbers"));
+        }
+        if line.to_uppercase().contains("PRINT")
+            && !line.contains('"')
+            && !line.chars().all(|c| c.is_alphanumeric() || c.is_whitespace() || c == '=' || c == '"')
+        {
+            diagnostics.push(error(line_num, "PRINT statement syntax error"));
+        }
+    }
+    diagnostics
+}
+
+fn check_pascal(code: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (line_num, line) in code.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.to_lowercase().starts_with("writeln") && (!line.contains('(') || !line.contains(')')) {
+            diagnostics.push(error(line_num, "writeln statement missing parentheses"));
+        }
+    }
+    diagnostics
+}
+
+fn check_prolog(code: &str) -> Vec<Diagnostic> {
+    match crate::prolog::load_program(code) {
+        Ok(_) => Vec::new(),
+        Err(message) => vec![error(0, format!("Prolog parse error: {}", message))],
+    }
+}
@@ -0,0 +1,239 @@
+//! Stateful, dialect-aware syntax-highlighting tokenizer for the code editor,
+//! ported in spirit from CodeMirror's `prolog.js` mode: a small state machine
+//! that recognizes comments, quoted atoms, strings, char codes, variables,
+//! numbers, and operators, carrying a per-line "continuation state" forward
+//! exactly like that mode does, since block comments and strings can span
+//! more than one line.
+
+use crate::repl::keywords_for;
+use eframe::egui::{self, Color32, FontId, TextFormat};
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Keyword,
+    Atom,
+    Variable,
+    Number,
+    String,
+    Comment,
+    Operator,
+    Text,
+}
+
+impl TokenKind {
+    fn color(self) -> Color32 {
+        match self {
+            TokenKind::Keyword => Color32::from_rgb(86, 156, 214),
+            TokenKind::Atom => Color32::from_rgb(206, 145, 120),
+            TokenKind::Variable => Color32::from_rgb(156, 220, 254),
+            TokenKind::Number => Color32::from_rgb(181, 206, 168),
+            TokenKind::String => Color32::from_rgb(214, 157, 133),
+            TokenKind::Comment => Color32::from_rgb(106, 153, 85),
+            TokenKind::Operator => Color32::from_rgb(212, 212, 212),
+            TokenKind::Text => Color32::LIGHT_GRAY,
+        }
+    }
+}
+
+/// Continuation state carried from the end of one line into the start of the
+/// next, for constructs that can span multiple lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum LineState {
+    #[default]
+    Normal,
+    InBlockComment,
+    InString,
+}
+
+const SYMBOL_CHARS: &str = "+-*/\\^<>=~:.?@#&;!";
+
+/// Tokenize one line starting from `start_state`, returning its `(range,
+/// kind)` spans (byte ranges are over `char` indices, not UTF-8 bytes) plus
+/// the continuation state the next line should start from.
+pub(crate) fn tokenize_line(line: &str, start_state: LineState, language: &str) -> (Vec<(Range<usize>, TokenKind)>, LineState) {
+    let chars: Vec<char> = line.chars().collect();
+    let keywords = keywords_for(language);
+    let mut spans = Vec::new();
+    let mut state = start_state;
+    let mut i = 0;
+
+    if state == LineState::InBlockComment {
+        let start = i;
+        while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+            i += 1;
+        }
+        if i < chars.len() {
+            i += 2;
+            state = LineState::Normal;
+        } else {
+            i = chars.len();
+        }
+        spans.push((start..i, TokenKind::Comment));
+    } else if state == LineState::InString {
+        let start = i;
+        while i < chars.len() && chars[i] != '"' {
+            i += 1;
+        }
+        if i < chars.len() {
+            i += 1;
+            state = LineState::Normal;
+        } else {
+            i = chars.len();
+        }
+        spans.push((start..i, TokenKind::String));
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '%' {
+            spans.push((i..chars.len(), TokenKind::Comment));
+            i = chars.len();
+            break;
+        }
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            let start = i;
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            if i < chars.len() {
+                i += 2;
+            } else {
+                state = LineState::InBlockComment;
+            }
+            spans.push((start..i, TokenKind::Comment));
+            continue;
+        }
+        if c == '\'' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != '\'' {
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            spans.push((start..i, TokenKind::Atom));
+            continue;
+        }
+        if c == '"' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    i += 1;
+                }
+                i += 1;
+            }
+            if i < chars.len() {
+                i += 1;
+            } else {
+                state = LineState::InString;
+            }
+            spans.push((start..i, TokenKind::String));
+            continue;
+        }
+        if c == '0' && chars.get(i + 1) == Some(&'\'') {
+            // `0'c` character code, as in `X is 0'a` (the code for 'a').
+            let start = i;
+            i += 2;
+            if i < chars.len() {
+                i += 1;
+            }
+            spans.push((start..i, TokenKind::Number));
+            continue;
+        }
+        if c.is_ascii_digit() {
+            let start = i;
+            if c == '0' && chars.get(i + 1) == Some(&'x') {
+                i += 2;
+                while i < chars.len() && chars[i].is_ascii_hexdigit() {
+                    i += 1;
+                }
+            } else {
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                if i < chars.len() && chars[i] == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) {
+                    i += 1;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            spans.push((start..i, TokenKind::Number));
+            continue;
+        }
+        if c.is_uppercase() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            spans.push((start..i, TokenKind::Variable));
+            continue;
+        }
+        if c.is_alphabetic() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let is_keyword = keywords.iter().any(|kw| kw.eq_ignore_ascii_case(&word));
+            spans.push((start..i, if is_keyword { TokenKind::Keyword } else { TokenKind::Atom }));
+            continue;
+        }
+        if SYMBOL_CHARS.contains(c) {
+            let start = i;
+            while i < chars.len() && SYMBOL_CHARS.contains(chars[i]) {
+                i += 1;
+            }
+            spans.push((start..i, TokenKind::Operator));
+            continue;
+        }
+        i += 1; // punctuation such as ( ) [ ] , |: left as default-colored text
+    }
+
+    (spans, state)
+}
+
+/// Build a colored `LayoutJob` for the whole buffer, carrying line-continuation
+/// state forward exactly like the CodeMirror mode does, for use as an egui
+/// `TextEdit` layouter.
+pub(crate) fn highlight_layout(code: &str, language: &str, font_id: FontId) -> egui::text::LayoutJob {
+    let mut job = egui::text::LayoutJob::default();
+    let mut state = LineState::Normal;
+    for (line_no, line) in code.split('\n').enumerate() {
+        if line_no > 0 {
+            job.append("\n", 0.0, TextFormat { font_id: font_id.clone(), color: TokenKind::Text.color(), ..Default::default() });
+        }
+        let (spans, next_state) = tokenize_line(line, state, language);
+        state = next_state;
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut last = 0;
+        for (range, kind) in spans {
+            if range.start > last {
+                append(&mut job, &chars[last..range.start], &font_id, TokenKind::Text.color());
+            }
+            append(&mut job, &chars[range.clone()], &font_id, kind.color());
+            last = range.end;
+        }
+        if last < chars.len() {
+            append(&mut job, &chars[last..], &font_id, TokenKind::Text.color());
+        }
+    }
+    job
+}
+
+fn append(job: &mut egui::text::LayoutJob, text: &[char], font_id: &FontId, color: Color32) {
+    if text.is_empty() {
+        return;
+    }
+    job.append(&text.iter().collect::<String>(), 0.0, TextFormat { font_id: font_id.clone(), color, ..Default::default() });
+}
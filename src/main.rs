@@ -2,6 +2,16 @@ use eframe::egui;
 use rfd::FileDialog;
 use std::collections::HashMap;
 
+mod cli;
+mod diagnostics;
+mod expr;
+mod flycheck;
+mod highlight;
+mod prolog;
+mod repl;
+use diagnostics::{ErrorKind, TimeWarpError};
+use expr::{Value, VarStore};
+
 #[derive(Clone)]
 struct TurtleState {
     x: f32,
@@ -11,26 +21,77 @@ struct TurtleState {
     color: egui::Color32,
 }
 
-struct TimeWarpApp {
-    code: String,
-    output: String,
-    language: String,
+/// Resumable TW BASIC program state: everything `execute_tw_basic` needs to
+/// pick a paused run back up at the exact line it stopped on, rather than
+/// re-parsing and re-running the whole program from line zero.
+struct BasicExecState {
+    lines: Vec<(u32, String)>,
+    line_numbers: HashMap<u32, usize>,
+    pilot_labels: HashMap<String, usize>,
+    for_stack: Vec<(String, f32, f32, f32)>,
+    gosub_stack: Vec<usize>,
+    i: usize,
+    output: Vec<String>,
+}
+
+/// Resumable TW Pascal program state, the `execute_tw_pascal` analogue of
+/// `BasicExecState`.
+struct PascalExecState {
+    lines: Vec<String>,
+    i: usize,
+    output: Vec<String>,
+}
+
+/// A paused interpreter run, kept on `TimeWarpApp` so `readln`/`INPUT` (and
+/// single-stepping) can suspend execution and resume from the exact point
+/// they left off instead of re-running the program from scratch. TW Prolog
+/// has no entry here: its SLD-resolution solver has no natural "next line"
+/// to pause on, so each query still runs to completion in one shot.
+enum ExecState {
+    Idle,
+    Basic(BasicExecState),
+    Pascal(PascalExecState),
+}
+
+pub(crate) struct TimeWarpApp {
+    pub(crate) code: String,
+    pub(crate) output: String,
+    pub(crate) language: String,
     active_tab: usize, // 0 = Editor, 1 = Output, 2 = Turtle
     code_history: Vec<String>,
     code_history_index: usize,
     last_file_path: Option<String>,
-    variables: HashMap<String, String>,
+    variables: VarStore,
     show_line_numbers: bool,
     find_text: String,
     replace_text: String,
     show_find_replace: bool,
     turtle_state: TurtleState,
-    turtle_commands: Vec<String>,
+    pub(crate) turtle_commands: Vec<String>,
     is_executing: bool,
-    waiting_for_input: bool,
-    input_prompt: String,
-    user_input: String,
+    pub(crate) waiting_for_input: bool,
+    pub(crate) input_prompt: String,
+    pub(crate) user_input: String,
     current_input_var: String,
+    pilot_last_answer: String,
+    pilot_match_flag: bool,
+    pub(crate) diagnostics: Vec<TimeWarpError>,
+    pub(crate) repl_input: String,
+    pub(crate) repl_log: String,
+    flycheck: flycheck::FlycheckWorker,
+    last_checked_code: String,
+    live_diagnostics: Vec<flycheck::Diagnostic>,
+    pub(crate) consult_stdlib: bool,
+    exec_state: ExecState,
+    single_step: bool,
+    // Set while `submit_repl_line` is feeding lines into `ExecState::Basic`
+    // piecemeal, so `execute_tw_basic` keeps the run's for/gosub stacks alive
+    // across a submission that finishes with no more lines queued yet,
+    // instead of resetting to `Idle` the way a one-shot Run does.
+    repl_basic_active: bool,
+    // Ctrl-R reverse history search in the REPL console (see `repl::reverse_search`).
+    reverse_search_active: bool,
+    reverse_search_query: String,
 }
 
 impl Default for TimeWarpApp {
@@ -61,13 +122,33 @@ impl Default for TimeWarpApp {
             input_prompt: String::new(),
             user_input: String::new(),
             current_input_var: String::new(),
+            pilot_last_answer: String::new(),
+            pilot_match_flag: false,
+            diagnostics: Vec::new(),
+            repl_input: String::new(),
+            repl_log: String::new(),
+            flycheck: flycheck::FlycheckWorker::spawn(),
+            last_checked_code: String::new(),
+            live_diagnostics: Vec::new(),
+            consult_stdlib: false,
+            exec_state: ExecState::Idle,
+            single_step: false,
+            repl_basic_active: false,
+            reverse_search_active: false,
+            reverse_search_query: String::new(),
         }
     }
 }
 
 impl TimeWarpApp {
-    fn execute_code(&mut self) {
+    pub(crate) fn execute_code(&mut self) {
         self.is_executing = true;
+        // Only wipe diagnostics when starting a fresh run: `execute_code` is also
+        // how a paused run (INPUT / single-step) resumes, and those calls should
+        // keep whatever errors were already collected earlier in the same run.
+        if matches!(self.exec_state, ExecState::Idle) {
+            self.diagnostics.clear();
+        }
         let code = self.code.clone(); // Clone to avoid borrowing conflict
         let result = match self.language.as_str() {
             "TW BASIC" => self.execute_tw_basic(&code),
@@ -77,49 +158,90 @@ impl TimeWarpApp {
         };
         if self.is_executing && !self.waiting_for_input {  // Only show result if not stopped and not waiting for input
             self.output = format!("[Output for {}]\n{}", self.language, result);
+            // Diagnostics are kept separately in `self.diagnostics` and rendered by
+            // the caller (colored egui labels here, ANSI color in the CLI).
             // Note: No longer auto-switching to output tab since tabs are always visible
         }
         self.is_executing = false;
     }
 
     fn execute_tw_basic(&mut self, code: &str) -> String {
-        let mut output = Vec::new();
-        let mut lines = Vec::new();
-        let mut line_numbers = std::collections::HashMap::new();
-        let mut _current_line = 0;
-        let mut for_stack = Vec::new();
-        let mut gosub_stack = Vec::new();
-
-        // Parse program - handle both line-numbered and non-line-numbered code
-        for (line_idx, line) in code.lines().enumerate() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with("REM") || line.starts_with("'") {
-                continue;
-            }
+        let (lines, line_numbers, pilot_labels, mut for_stack, mut gosub_stack, mut i, mut output) =
+            match std::mem::replace(&mut self.exec_state, ExecState::Idle) {
+                ExecState::Basic(state) => (
+                    state.lines,
+                    state.line_numbers,
+                    state.pilot_labels,
+                    state.for_stack,
+                    state.gosub_stack,
+                    state.i,
+                    state.output,
+                ),
+                other => {
+                    // Not resuming a paused BASIC run (or a different language's
+                    // run was paused) - parse the program fresh, same as before.
+                    self.exec_state = other;
+                    let mut lines = Vec::new();
+                    let mut line_numbers = std::collections::HashMap::new();
+
+                    // Parse program - handle both line-numbered and non-line-numbered code
+                    for (line_idx, line) in code.lines().enumerate() {
+                        let line = line.trim();
+                        if line.is_empty() || line.starts_with("REM") || line.starts_with("'") {
+                            continue;
+                        }
 
-            // Check if it's a line-numbered BASIC program
-            if let Some(space_pos) = line.find(' ') {
-                if let Ok(line_num) = line[..space_pos].parse::<u32>() {
-                    let command = line[space_pos..].trim();
-                    lines.push((line_num, command.to_string()));
-                    line_numbers.insert(line_num, line_idx);
-                    continue;
-                }
-            }
+                        // Check if it's a line-numbered BASIC program
+                        if let Some(space_pos) = line.find(' ') {
+                            if let Ok(line_num) = line[..space_pos].parse::<u32>() {
+                                let command = line[space_pos..].trim();
+                                lines.push((line_num, command.to_string()));
+                                line_numbers.insert(line_num, line_idx);
+                                continue;
+                            }
+                        }
 
-            // Non-line-numbered code (PILOT, Logo, or modern BASIC)
-            lines.push((line_idx as u32, line.to_string()));
-        }
+                        // Non-line-numbered code (PILOT, Logo, or modern BASIC)
+                        lines.push((line_idx as u32, line.to_string()));
+                    }
 
-        // Sort by line number for line-numbered programs
-        if lines.iter().any(|(num, _)| *num > 0) {
-            lines.sort_by_key(|(num, _)| *num);
-        }
+                    // Sort by line number for line-numbered programs
+                    if lines.iter().any(|(num, _)| *num > 0) {
+                        lines.sort_by_key(|(num, _)| *num);
+                    }
+
+                    // PILOT labels ("*LABEL") are resolved by index into `lines`, post-sort,
+                    // so J: can jump to them the same way GOTO jumps to a line number.
+                    let mut pilot_labels: HashMap<String, usize> = HashMap::new();
+                    for (idx, (_, command)) in lines.iter().enumerate() {
+                        if let Some(label) = command.trim().strip_prefix('*') {
+                            pilot_labels.insert(label.trim().to_uppercase(), idx);
+                        }
+                    }
+
+                    (lines, line_numbers, pilot_labels, Vec::new(), Vec::new(), 0, Vec::new())
+                }
+            };
+
+        // Diagnostics are anchored to the real source line: line-numbered BASIC
+        // stores the BASIC line number in `line_num`, so resolve it back through
+        // `line_numbers`; non-numbered code already uses the source index directly.
+        let source_line_of =
+            |line_num: u32| -> usize { line_numbers.get(&line_num).copied().unwrap_or(line_num as usize) };
 
-        let mut i = 0;
+        let mut executed_one = false;
         while i < lines.len() {
+            // Suspend here rather than running further: either a line just set
+            // `waiting_for_input` (INPUT / PILOT A:) and the UI needs to collect
+            // an answer, or we're single-stepping and already ran one statement
+            // this call.
+            if self.waiting_for_input || (self.single_step && executed_one) {
+                break;
+            }
+            executed_one = true;
             let (line_num, command) = &lines[i];
-            _current_line = *line_num;
+            let _current_line = *line_num;
+            let src_line = source_line_of(*line_num);
 
             let cmd_upper = command.to_uppercase();
             let cmd_trim = command.trim();
@@ -127,10 +249,10 @@ impl TimeWarpApp {
             // GW BASIC Commands
             if cmd_upper.starts_with("PRINT") || cmd_upper.starts_with("?") {
                 let print_cmd = if cmd_upper.starts_with("?") { &command[1..] } else { &command[6..] };
-                self.execute_print(&mut output, print_cmd.trim());
+                self.execute_print(src_line, &mut output, print_cmd.trim());
             }
             else if cmd_upper.starts_with("LET ") {
-                self.execute_let(&mut output, &command[4..]);
+                self.execute_let(src_line, &mut output, &command[4..]);
             }
             else if cmd_upper.starts_with("INPUT") {
                 self.execute_input(&mut output, &command[6..]);
@@ -139,7 +261,7 @@ impl TimeWarpApp {
                 if let Some(then_pos) = cmd_upper.find(" THEN ") {
                     let condition = &command[3..then_pos];
                     let then_part = &command[then_pos + 6..];
-                    if self.evaluate_condition(condition) {
+                    if self.evaluate_condition(src_line, condition) {
                         if let Ok(line_num) = then_part.trim().parse::<u32>() {
                             // GOTO line number
                             if let Some(&new_i) = line_numbers.get(&line_num) {
@@ -148,7 +270,7 @@ impl TimeWarpApp {
                             }
                         } else {
                             // Execute inline command
-                            self.execute_basic_command(&mut output, then_part.trim());
+                            self.execute_basic_command(src_line, &mut output, then_part.trim());
                         }
                     }
                 }
@@ -175,12 +297,27 @@ impl TimeWarpApp {
                     i = return_i;
                     continue;
                 }
+                self.diagnostics.push(TimeWarpError::new(
+                    source_line_of(*line_num),
+                    0,
+                    command.len(),
+                    ErrorKind::UnmatchedControlFlow,
+                    "RETURN without a matching GOSUB",
+                ));
             }
             else if cmd_upper.starts_with("FOR ") {
-                self.execute_for(&mut output, &command[4..], &mut for_stack);
-            }
-            else if cmd_upper == "NEXT" {
-                if self.execute_next(&mut output, &mut for_stack) {
+                self.execute_for(src_line, &mut output, &command[4..], &mut for_stack);
+            }
+            else if cmd_upper == "NEXT" || cmd_upper.starts_with("NEXT ") {
+                if for_stack.is_empty() {
+                    self.diagnostics.push(TimeWarpError::new(
+                        source_line_of(*line_num),
+                        0,
+                        command.len(),
+                        ErrorKind::UnmatchedControlFlow,
+                        "NEXT without a matching FOR",
+                    ));
+                } else if self.execute_next(&mut output, &mut for_stack) {
                     // Continue with the loop
                     continue;
                 }
@@ -188,7 +325,7 @@ impl TimeWarpApp {
             else if cmd_upper.starts_with("WHILE ") {
                 // Simple WHILE implementation
                 let condition = &command[6..];
-                if !self.evaluate_condition(condition) {
+                if !self.evaluate_condition(src_line, condition) {
                     // Skip to WEND
                     let mut nest_level = 1;
                     while i + 1 < lines.len() && nest_level > 0 {
@@ -215,15 +352,22 @@ impl TimeWarpApp {
                         nest_level -= 1;
                     }
                 }
-                if while_i < i {
-                    let (_, while_cmd) = &lines[while_i];
-                    if while_cmd.to_uppercase().starts_with("WHILE ") {
-                        let condition = &while_cmd[6..];
-                        if self.evaluate_condition(condition) {
-                            i = while_i;
-                            continue;
-                        }
+                let matched_while = while_i < i && lines[while_i].1.to_uppercase().starts_with("WHILE ");
+                if matched_while {
+                    let (while_line_num, while_cmd) = &lines[while_i];
+                    let condition = &while_cmd[6..];
+                    if self.evaluate_condition(source_line_of(*while_line_num), condition) {
+                        i = while_i;
+                        continue;
                     }
+                } else {
+                    self.diagnostics.push(TimeWarpError::new(
+                        source_line_of(*line_num),
+                        0,
+                        command.len(),
+                        ErrorKind::UnmatchedControlFlow,
+                        "WEND without a matching WHILE",
+                    ));
                 }
             }
             else if cmd_upper.starts_with("CLS") {
@@ -243,42 +387,69 @@ impl TimeWarpApp {
                 output.push(format!("Sound: {}", &command[6..]));
             }
 
-            // PILOT Commands
-            else if cmd_trim.starts_with("T:") {
-                let text = &cmd_trim[2..].trim();
-                output.push(format!("QUESTION: {}", text));
+            // PILOT label definitions (e.g. "*INTRO") are jump targets, not instructions.
+            else if cmd_trim.starts_with('*') {
             }
-            else if cmd_trim.starts_with("A:") {
-                let text = &cmd_trim[2..].trim();
-                output.push(format!("ACCEPT: {}", text));
-                // In a real implementation, this would wait for user input
-                output.push("(Waiting for user input...)".to_string());
-            }
-            else if cmd_trim.starts_with("J:") {
-                let jump_target = &cmd_trim[2..].trim();
-                if let Ok(line_num) = jump_target.parse::<u32>() {
-                    if let Some(&new_i) = line_numbers.get(&line_num) {
-                        i = new_i;
-                        continue;
+
+            // PILOT Commands: T: A: M: J: U: Y: N:, each optionally suffixed with a
+            // Y/N conditioner (TY:, JN:, ...) that gates execution on the match flag.
+            else if let Some((letter, conditioner, text)) = parse_pilot_command(cmd_trim) {
+                let should_run = conditioner.map_or(true, |want| self.pilot_match_flag == want);
+                if should_run {
+                    match letter {
+                        'T' => {
+                            let text = expr::interpolate(text.trim(), &self.variables);
+                            output.push(format!("QUESTION: {}", text));
+                        }
+                        'A' => {
+                            let var_name = text.trim().to_string();
+                            self.input_prompt = format!("{}?", var_name);
+                            self.current_input_var = var_name;
+                            self.waiting_for_input = true;
+                            output.push(format!("ACCEPT: {}", self.input_prompt));
+                        }
+                        'M' => {
+                            let answer = self.pilot_last_answer.to_uppercase();
+                            self.pilot_match_flag = text
+                                .split(',')
+                                .map(|pattern| pattern.trim().to_uppercase())
+                                .filter(|pattern| !pattern.is_empty())
+                                .any(|pattern| answer.contains(&pattern));
+                            output.push(format!(
+                                "MATCH: {} -> {}",
+                                text.trim(),
+                                if self.pilot_match_flag { "yes" } else { "no" }
+                            ));
+                        }
+                        'J' => {
+                            let target = text.trim();
+                            let destination = if let Some(label) = target.strip_prefix('*') {
+                                pilot_labels.get(&label.trim().to_uppercase()).copied()
+                            } else if let Ok(line_num) = target.parse::<u32>() {
+                                line_numbers.get(&line_num).copied()
+                            } else {
+                                pilot_labels.get(&target.to_uppercase()).copied()
+                            };
+                            if let Some(new_i) = destination {
+                                i = new_i;
+                                continue;
+                            }
+                        }
+                        'U' => {
+                            output.push(format!("USE: {}", text.trim()));
+                        }
+                        'Y' => {
+                            let text = expr::interpolate(text.trim(), &self.variables);
+                            output.push(format!("YES: {}", text));
+                        }
+                        'N' => {
+                            let text = expr::interpolate(text.trim(), &self.variables);
+                            output.push(format!("NO: {}", text));
+                        }
+                        _ => {}
                     }
                 }
             }
-            else if cmd_trim.starts_with("M:") {
-                let match_text = &cmd_trim[2..].trim();
-                output.push(format!("MATCH: {}", match_text));
-            }
-            else if cmd_trim.starts_with("U:") {
-                let use_text = &cmd_trim[2..].trim();
-                output.push(format!("USE: {}", use_text));
-            }
-            else if cmd_trim.starts_with("Y:") {
-                let yes_text = &cmd_trim[2..].trim();
-                output.push(format!("YES: {}", yes_text));
-            }
-            else if cmd_trim.starts_with("N:") {
-                let no_text = &cmd_trim[2..].trim();
-                output.push(format!("NO: {}", no_text));
-            }
 
             // Logo Commands
             else if cmd_upper.starts_with("FORWARD ") || cmd_upper.starts_with("FD ") {
@@ -356,7 +527,7 @@ impl TimeWarpApp {
                     let var_name = &rest[..quote_pos].trim();
                     if let Some(end_quote) = rest[quote_pos + 1..].find('"') {
                         let value = &rest[quote_pos + 1..quote_pos + 1 + end_quote];
-                        self.variables.insert(var_name.to_string(), value.to_string());
+                        self.variables.insert(var_name.to_string(), Value::Str(value.to_string()));
                         output.push(format!("{} = \"{}\"", var_name, value));
                     }
                 }
@@ -367,7 +538,7 @@ impl TimeWarpApp {
                     if let Ok(count) = command[7..7 + space_pos].trim().parse::<u32>() {
                         let repeat_cmd = &command[7 + space_pos + 1..];
                         for _ in 0..count {
-                            self.execute_basic_command(&mut output, repeat_cmd.trim());
+                            self.execute_basic_command(src_line, &mut output, repeat_cmd.trim());
                         }
                     }
                 }
@@ -376,44 +547,68 @@ impl TimeWarpApp {
             // Unknown command
             else if !command.is_empty() {
                 output.push(format!("Unknown command: {}", command));
+                self.diagnostics.push(TimeWarpError::new(
+                    source_line_of(*line_num),
+                    0,
+                    command.len(),
+                    ErrorKind::UnknownCommand,
+                    format!("unknown command `{}`", command),
+                ));
             }
 
             i += 1;
         }
 
+        if i < lines.len() || self.waiting_for_input || self.repl_basic_active {
+            // Paused (waiting for input, even on the last line, or single-stepped
+            // before reaching the end of the program) - keep everything needed
+            // to pick back up here.
+            // A REPL session also keeps this alive once it runs dry, so a FOR
+            // or GOSUB opened on one submitted line can be closed by a NEXT or
+            // RETURN on a later one (see `feed_repl_line_basic`).
+            self.exec_state = ExecState::Basic(BasicExecState { lines, line_numbers, pilot_labels, for_stack, gosub_stack, i, output: output.clone() });
+        }
+
         output.join("\n")
     }
 
-    fn execute_print(&mut self, output: &mut Vec<String>, args: &str) {
+    fn execute_print(&mut self, line: usize, output: &mut Vec<String>, args: &str) {
         if args.trim().is_empty() {
             output.push("".to_string());
             return;
         }
 
-        if let Some(quote_start) = args.find('"') {
-            if let Some(quote_end) = args[quote_start + 1..].find('"') {
-                let text = &args[quote_start + 1..quote_start + 1 + quote_end];
-                output.push(text.to_string());
-                return;
+        let mut parts = Vec::new();
+        for part in split_top_level(args, ',') {
+            let part = part.trim();
+            match expr::eval_str(part, &self.variables) {
+                Ok(value) => parts.push(value.display()),
+                Err(err) => self.diagnostics.push(TimeWarpError::new(line, 0, part.len(), classify_expr_error(&err), err)),
             }
         }
-
-        // Handle variable printing
-        let var_name = args.trim();
-        if let Some(value) = self.variables.get(var_name) {
-            output.push(value.clone());
-        } else {
-            output.push(format!("Undefined variable: {}", var_name));
-        }
+        output.push(parts.join(" "));
     }
 
-    fn execute_let(&mut self, output: &mut Vec<String>, args: &str) {
+    fn execute_let(&mut self, line: usize, output: &mut Vec<String>, args: &str) {
         if let Some(eq_pos) = args.find('=') {
             let var_part = &args[..eq_pos].trim();
             let value_part = args[eq_pos + 1..].trim();
             if let Some(var_name) = var_part.split_whitespace().last() {
-                self.variables.insert(var_name.to_string(), value_part.to_string());
-                output.push(format!("{} = {}", var_name, value_part));
+                match expr::eval_str(value_part, &self.variables) {
+                    Ok(value) => {
+                        output.push(format!("{} = {}", var_name, value.display()));
+                        self.variables.insert(var_name.to_string(), value);
+                    }
+                    Err(err) => {
+                        self.diagnostics.push(TimeWarpError::new(
+                            line,
+                            0,
+                            value_part.len(),
+                            classify_expr_error(&err),
+                            err,
+                        ));
+                    }
+                }
             }
         }
     }
@@ -449,42 +644,66 @@ impl TimeWarpApp {
         // Continue execution - input will be processed later
     }
 
-    fn evaluate_condition(&self, condition: &str) -> bool {
-        // Simple condition evaluation
-        if condition.contains("= ") {
-            let parts: Vec<&str> = condition.split("= ").collect();
-            if parts.len() == 2 {
-                let left = parts[0].trim();
-                let right = parts[1].trim();
-
-                if let Some(left_val) = self.variables.get(left) {
-                    return left_val == right;
-                }
+    fn evaluate_condition(&mut self, line: usize, condition: &str) -> bool {
+        match expr::eval_str(condition, &self.variables) {
+            Ok(value) => value.is_truthy(),
+            Err(err) => {
+                self.diagnostics.push(TimeWarpError::new(line, 0, condition.len(), classify_expr_error(&err), err));
+                // Default to true: an IF with a malformed condition runs its THEN
+                // part, and a WHILE with one keeps looping until stopped.
+                true
             }
         }
-        // Default to true for simple conditions
-        true
     }
 
-    fn execute_basic_command(&mut self, output: &mut Vec<String>, command: &str) {
+    fn execute_basic_command(&mut self, line: usize, output: &mut Vec<String>, command: &str) {
         let cmd_upper = command.to_uppercase();
         if cmd_upper.starts_with("PRINT") {
-            self.execute_print(output, &command[6..]);
+            self.execute_print(line, output, &command[6..]);
         } else if cmd_upper.starts_with("LET ") {
-            self.execute_let(output, &command[4..]);
+            self.execute_let(line, output, &command[4..]);
         }
         // Add other commands as needed
     }
 
-    fn execute_for(&mut self, output: &mut Vec<String>, args: &str, for_stack: &mut Vec<(String, f32, f32, f32)>) {
-        // Simple FOR loop implementation: FOR I = 1 TO 10
-        let parts: Vec<&str> = args.split_whitespace().collect();
-        if parts.len() >= 5 && parts[1] == "=" && parts[3] == "TO" {
-            let var_name = parts[0];
-            if let (Ok(start), Ok(end)) = (parts[2].parse::<f32>(), parts[4].parse::<f32>()) {
-                self.variables.insert(var_name.to_string(), start.to_string());
-                for_stack.push((var_name.to_string(), start, end, 1.0)); // step = 1
-                output.push(format!("FOR {} = {} TO {}", var_name, start, end));
+    fn execute_for(&mut self, line: usize, output: &mut Vec<String>, args: &str, for_stack: &mut Vec<(String, f32, f32, f32)>) {
+        // FOR <var> = <expr> TO <expr> [STEP <expr>]
+        let Some(eq_pos) = args.find('=') else { return };
+        let var_name = args[..eq_pos].trim().to_string();
+        let rest = &args[eq_pos + 1..];
+        let Some(to_pos) = find_keyword(rest, "TO") else { return };
+        let start_expr = &rest[..to_pos];
+        let after_to = &rest[to_pos + 2..];
+        let (end_expr, step_expr) = match find_keyword(after_to, "STEP") {
+            Some(step_pos) => (&after_to[..step_pos], Some(&after_to[step_pos + 4..])),
+            None => (after_to, None),
+        };
+
+        let start = self.eval_for_bound(line, start_expr);
+        let end = self.eval_for_bound(line, end_expr);
+        let step = match step_expr {
+            Some(s) => self.eval_for_bound(line, s),
+            None => Some(1.0),
+        };
+        if let (Some(start), Some(end), Some(step)) = (start, end, step) {
+            self.variables.insert(var_name.clone(), Value::Number(start as f64));
+            for_stack.push((var_name.clone(), start, end, step));
+            output.push(format!("FOR {} = {} TO {}", var_name, start, end));
+        }
+    }
+
+    fn eval_for_bound(&mut self, line: usize, expr_src: &str) -> Option<f32> {
+        let expr_src = expr_src.trim();
+        match expr::eval_str(expr_src, &self.variables) {
+            Ok(Value::Number(n)) => Some(n as f32),
+            Ok(other) => {
+                let message = format!("Expected a number, found {}", other.display());
+                self.diagnostics.push(TimeWarpError::new(line, 0, expr_src.len(), ErrorKind::TypeMismatch, message));
+                None
+            }
+            Err(err) => {
+                self.diagnostics.push(TimeWarpError::new(line, 0, expr_src.len(), classify_expr_error(&err), err));
+                None
             }
         }
     }
@@ -492,11 +711,12 @@ impl TimeWarpApp {
     fn execute_next(&mut self, output: &mut Vec<String>, for_stack: &mut Vec<(String, f32, f32, f32)>) -> bool {
         if let Some((var_name, current, end, step)) = for_stack.last().cloned() {
             let new_current = current + step;
-            if new_current <= end {
+            let loop_continues = if step >= 0.0 { new_current <= end } else { new_current >= end };
+            if loop_continues {
                 // Update the last element
                 if let Some(last) = for_stack.last_mut() {
                     last.1 = new_current;
-                    self.variables.insert(var_name.clone(), new_current.to_string());
+                    self.variables.insert(var_name.clone(), Value::Number(new_current as f64));
                     output.push(format!("NEXT {} = {}", var_name, new_current));
                     return true; // Continue loop
                 }
@@ -510,16 +730,25 @@ impl TimeWarpApp {
     }
 
     fn execute_tw_pascal(&mut self, code: &str) -> String {
-        let mut output = Vec::new();
-        let lines: Vec<&str> = code.lines().collect();
+        let (lines, mut i, mut output) = match std::mem::replace(&mut self.exec_state, ExecState::Idle) {
+            ExecState::Pascal(state) => (state.lines, state.i, state.output),
+            other => {
+                self.exec_state = other;
+                (code.lines().map(str::to_string).collect::<Vec<_>>(), 0, Vec::new())
+            }
+        };
 
-        let mut i = 0;
+        let mut executed_one = false;
         while i < lines.len() {
+            if self.waiting_for_input || (self.single_step && executed_one) {
+                break;
+            }
             let line = lines[i].trim();
             if line.is_empty() || line.to_lowercase().starts_with("(*") || line.starts_with("{") {
                 i += 1;
                 continue;
             }
+            executed_one = true;
 
             let line_lower = line.to_lowercase();
 
@@ -528,15 +757,14 @@ impl TimeWarpApp {
                 if let Some(quote_start) = line.find('\'') {
                     if let Some(quote_end) = line[quote_start + 1..].find('\'') {
                         let text = &line[quote_start + 1..quote_start + 1 + quote_end];
-                        output.push(text.to_string());
+                        output.push(expr::interpolate(text, &self.variables));
                     }
                 } else if line.contains(");") {
                     // Handle variable output
                     let var_part = &line[8..line.len() - 2]; // Remove "writeln(" and ");"
-                    if let Some(value) = self.variables.get(var_part.trim()) {
-                        output.push(value.clone());
-                    } else {
-                        output.push(format!("Undefined variable: {}", var_part.trim()));
+                    match expr::eval_str(var_part.trim(), &self.variables) {
+                        Ok(value) => output.push(value.display()),
+                        Err(err) => output.push(err),
                     }
                 }
             }
@@ -544,7 +772,7 @@ impl TimeWarpApp {
                 if let Some(quote_start) = line.find('\'') {
                     if let Some(quote_end) = line[quote_start + 1..].find('\'') {
                         let text = &line[quote_start + 1..quote_start + 1 + quote_end];
-                        output.push(text.to_string());
+                        output.push(expr::interpolate(text, &self.variables));
                     }
                 }
             }
@@ -642,9 +870,14 @@ impl TimeWarpApp {
                 let parts: Vec<&str> = line.split(":=").collect();
                 if parts.len() == 2 {
                     let var_name = parts[0].trim();
-                    let value = parts[1].trim().trim_end_matches(';');
-                    self.variables.insert(var_name.to_string(), value.to_string());
-                    output.push(format!("{} := {}", var_name, value));
+                    let value_src = parts[1].trim().trim_end_matches(';');
+                    match expr::eval_str(value_src, &self.variables) {
+                        Ok(value) => {
+                            output.push(format!("{} := {}", var_name, value.display()));
+                            self.variables.insert(var_name.to_string(), value);
+                        }
+                        Err(err) => output.push(format!("Error in assignment: {}", err)),
+                    }
                 }
             }
 
@@ -656,144 +889,69 @@ impl TimeWarpApp {
             i += 1; // Move to next line
         }
 
+        if i < lines.len() || self.waiting_for_input {
+            // Paused on a `readln` or a single step - remember where to resume.
+            self.exec_state = ExecState::Pascal(PascalExecState { lines, i, output: output.clone() });
+        }
+
         output.join("\n")
     }
 
+    /// Run a TW Prolog program through a real SLD-resolution engine
+    /// (`prolog::load_program`/`prolog::query_with_output`) instead of the old
+    /// line-by-line pattern labeling. Turbo Prolog's `domains`/`predicates`/
+    /// `clauses` section headers are still recognized and skipped; a `goal`
+    /// section's lines are run as queries, the same role it plays in real
+    /// Turbo Prolog, and `:- Goal.` directives work anywhere in the source.
     fn execute_tw_prolog(&mut self, code: &str) -> String {
         let mut output = Vec::new();
-        let mut predicates = std::collections::HashMap::new();
-        let lines: Vec<&str> = code.lines().collect();
+        let mut in_goal_section = false;
+        let mut source = String::new();
 
-        let mut i = 0;
-        while i < lines.len() {
-            let line = lines[i].trim();
+        for raw_line in code.lines() {
+            let line = raw_line.trim();
             if line.is_empty() || line.starts_with('%') || line.starts_with("/*") {
-                i += 1;
                 continue;
             }
-
-            // Turbo Prolog domains (type declarations)
-            if line.to_lowercase().starts_with("domains") {
-                output.push("DOMAINS section:".to_string());
-            }
-            else if line.to_lowercase().starts_with("predicates") {
-                output.push("PREDICATES section:".to_string());
-            }
-            else if line.to_lowercase().starts_with("goal") {
-                output.push("GOAL section:".to_string());
-            }
-            else if line.to_lowercase().starts_with("clauses") {
-                output.push("CLAUSES section:".to_string());
+            let lower = line.to_lowercase();
+            if lower.starts_with("domains") || lower.starts_with("predicates") || lower.starts_with("clauses") {
+                in_goal_section = false;
+                continue;
             }
-
-            // Turbo Prolog domain declarations
-            else if line.contains("=") && !line.contains(":-") && !line.contains("(") {
-                output.push(format!("Domain: {}", line));
+            if lower.starts_with("goal") {
+                in_goal_section = true;
+                continue;
             }
-
-            // Turbo Prolog predicate declarations
-            else if line.contains("(") && line.contains(")") && !line.contains(":-") && !line.contains(".") {
-                let pred_name = if let Some(paren_pos) = line.find('(') {
-                    &line[..paren_pos].trim()
-                } else {
-                    line
-                };
-                output.push(format!("Predicate declared: {}", pred_name));
-                predicates.insert(pred_name.to_string(), Vec::new());
+            if in_goal_section && !line.starts_with(":-") {
+                source.push_str(":- ");
             }
+            source.push_str(line);
+            source.push('\n');
+        }
 
-            // Turbo Prolog facts and rules
-            else if line.contains(":-") {
-                let parts: Vec<&str> = line.split(":-").collect();
-                if parts.len() == 2 {
-                    let head = parts[0].trim();
-                    let body = parts[1].trim().trim_end_matches('.');
-                    output.push(format!("Rule: {} :- {}", head, body));
-
-                    // Store the rule
-                    if let Some(pred_name) = head.split('(').next() {
-                        if let Some(rules) = predicates.get_mut(pred_name.trim()) {
-                            rules.push(format!("{} :- {}", head, body));
-                        }
-                    }
-                }
-            }
-            else if line.ends_with('.') && line.contains('(') {
-                let fact = line.trim_end_matches('.');
-                output.push(format!("Fact: {}", fact));
-
-                // Store the fact
-                if let Some(pred_name) = fact.split('(').next() {
-                    if let Some(facts) = predicates.get_mut(pred_name.trim()) {
-                        facts.push(fact.to_string());
-                    }
-                }
-            }
-
-            // Turbo Prolog queries/goals
-            else if line.ends_with('.') && !line.contains('(') && !line.contains(":-") {
-                let query = line.trim_end_matches('.');
-                output.push(format!("Query: {}", query));
-
-                // Simple query resolution simulation
-                if let Some(pred_name) = query.split('(').next() {
-                    if let Some(rules_facts) = predicates.get(pred_name.trim()) {
-                        if !rules_facts.is_empty() {
-                            output.push(format!("  Found {} clause(s) for {}", rules_facts.len(), pred_name));
-                            for clause in rules_facts {
-                                output.push(format!("    {}", clause));
-                            }
-                        } else {
-                            output.push(format!("  No clauses found for {}", pred_name));
-                        }
-                    } else {
-                        output.push(format!("  Unknown predicate: {}", pred_name));
-                    }
-                }
-            }
+        let (db, queries) = match prolog::load_program_with_stdlib(&source, self.consult_stdlib) {
+            Ok(parsed) => parsed,
+            Err(err) => return format!("Parse error: {}", err),
+        };
 
-            // Turbo Prolog built-in predicates
-            else if line.to_lowercase().contains("write(") {
-                let content = if let Some(start) = line.find('"') {
-                    if let Some(end) = line[start + 1..].find('"') {
-                        &line[start + 1..start + 1 + end]
-                    } else {
-                        "unknown"
-                    }
-                } else {
-                    "variable"
-                };
-                output.push(format!("WRITE: {}", content));
-            }
-            else if line.to_lowercase().contains("nl") {
-                output.push("NEWLINE".to_string());
-            }
-            else if line.to_lowercase().contains("readln(") {
-                // Set up interactive input for Prolog readln
-                self.input_prompt = "Enter value for readln:".to_string();
-                self.current_input_var = "READLN".to_string(); // Generic variable for Prolog
-                self.waiting_for_input = true;
-                output.push(format!("{} ", self.input_prompt));
-                // Continue execution - input will be processed later
-            }
+        if queries.is_empty() {
+            return "Consulted program with no query to run (add a `goal` section or `:- Goal.` directive).".to_string();
+        }
 
-            // Turbo Prolog arithmetic and comparison
-            else if line.contains("+") || line.contains("-") || line.contains("*") || line.contains("/") {
-                output.push(format!("Arithmetic expression: {}", line));
+        for goal in &queries {
+            let (solutions, written) = prolog::query_with_output(&db, goal, 10);
+            if !written.is_empty() {
+                output.push(written);
             }
-            else if line.contains("=") || line.contains(">") || line.contains("<") {
-                output.push(format!("Comparison: {}", line));
-            }
-
-            // Unknown Prolog code
-            else if !line.is_empty() && !line.ends_with('.') {
-                output.push(format!("Prolog statement: {}", line));
+            if solutions.is_empty() {
+                output.push(format!("?- {}\nfalse.", prolog::format_term(goal)));
+            } else {
+                let rendered: Vec<String> = solutions.iter().map(|s| prolog::format_solution(s)).collect();
+                output.push(format!("?- {}\n{}.", prolog::format_term(goal), rendered.join(" ;\n")));
             }
-
-            i += 1; // Move to next line
         }
 
-        output.join("\n")
+        output.join("\n\n")
     }
 
     fn find_and_replace(&mut self) {
@@ -965,22 +1123,43 @@ impl TimeWarpApp {
         if self.is_executing {
             self.is_executing = false;
             self.output = "Program execution stopped by user.".to_string();
+            self.exec_state = ExecState::Idle;
+            self.repl_basic_active = false;
+        }
+    }
+
+    /// Append a line to whichever interpreter's paused run is live, so it
+    /// shows up in the cumulative transcript on resume instead of being
+    /// clobbered the next time `execute_code` rebuilds `self.output` from the
+    /// persisted state.
+    fn push_exec_output(&mut self, line: String) {
+        match &mut self.exec_state {
+            ExecState::Basic(state) => state.output.push(line),
+            ExecState::Pascal(state) => state.output.push(line),
+            ExecState::Idle => {}
         }
     }
 
-    fn process_user_input(&mut self) {
+    pub(crate) fn process_user_input(&mut self) {
         // Store the input in the appropriate variable based on context
         if !self.user_input.is_empty() {
-            // Add the input to output for confirmation
-            self.output.push_str(&format!("\n> {}", self.user_input));
+            // Add the input to the paused run's output for confirmation.
+            self.push_exec_output(format!("> {}", self.user_input));
 
-            // Store in the variable that was waiting for input
+            // PILOT's M: matches against whatever the last A: accepted.
+            self.pilot_last_answer = self.user_input.clone();
+
+            // Store in the variable that was waiting for input, numeric if it parses as one
+            let value = match self.user_input.parse::<f64>() {
+                Ok(n) => Value::Number(n),
+                Err(_) => Value::Str(self.user_input.clone()),
+            };
             if !self.current_input_var.is_empty() {
-                self.variables.insert(self.current_input_var.clone(), self.user_input.clone());
-                self.output.push_str(&format!("\nStored in variable: {}", self.current_input_var));
+                self.variables.insert(self.current_input_var.clone(), value);
+                self.push_exec_output(format!("Stored in variable: {}", self.current_input_var));
             } else {
                 // Fallback for cases where variable name wasn't captured
-                self.variables.insert("INPUT".to_string(), self.user_input.clone());
+                self.variables.insert("INPUT".to_string(), value);
             }
 
             // Reset input state
@@ -989,10 +1168,189 @@ impl TimeWarpApp {
             self.user_input.clear();
             self.current_input_var.clear();
 
-            // Re-run execution with input value available
+            // Resume the paused interpreter from the exact point it suspended
+            // at, rather than re-running the whole program from the top.
+            self.single_step = false;
             self.execute_code();
         }
     }
+
+    /// Run exactly one statement of the paused (or freshly started) program,
+    /// then pause again so the variable table can be inspected between steps.
+    pub(crate) fn step(&mut self) {
+        self.single_step = true;
+        self.execute_code();
+        self.single_step = false;
+    }
+
+    /// Resume a paused program and run until it either finishes or suspends
+    /// again on the next `INPUT`/`readln`.
+    pub(crate) fn continue_execution(&mut self) {
+        self.single_step = false;
+        self.execute_code();
+    }
+
+    /// Execute `self.repl_input` as one REPL statement against the persistent
+    /// interpreter state (variables, turtle state carry over between lines),
+    /// recording it in the shared `code_history` ring and appending the
+    /// exchange to `repl_log`.
+    ///
+    /// TW BASIC gets special treatment: a line like `NEXT I` is meaningless
+    /// on its own, so instead of replacing `self.code` with just this line we
+    /// feed it into the same resumable `ExecState::Basic` that chunk1-6 built
+    /// for `INPUT` suspension, letting a `FOR`/`NEXT` (or `GOSUB`/`RETURN`,
+    /// `WHILE`/`WEND`) construct span several separately-submitted lines. TW
+    /// Pascal/Prolog queries are self-contained per line, so they keep
+    /// executing each submission as an independent one-liner.
+    pub(crate) fn submit_repl_line(&mut self) {
+        let line = self.repl_input.trim().to_string();
+        if line.is_empty() {
+            return;
+        }
+
+        self.push_repl_history(line.clone());
+        self.repl_log.push_str(&format!("{}> {}\n", self.language, line));
+        if self.language == "TW BASIC" {
+            self.feed_repl_line_basic(&line);
+        } else {
+            self.code = line;
+        }
+        self.execute_code();
+        self.repl_log.push_str(&self.output);
+        self.repl_log.push('\n');
+        self.repl_input.clear();
+    }
+
+    /// Append one REPL-submitted line to the running `ExecState::Basic`
+    /// program (starting a fresh one if the previous run finished or none is
+    /// paused), so `execute_tw_basic`'s resume branch can pick up right where
+    /// the last submission left off instead of re-parsing a single isolated
+    /// line.
+    fn feed_repl_line_basic(&mut self, line: &str) {
+        if line.is_empty() || line.starts_with("REM") || line.starts_with('\'') {
+            return;
+        }
+        self.repl_basic_active = true;
+
+        let mut state = match std::mem::replace(&mut self.exec_state, ExecState::Idle) {
+            ExecState::Basic(state) => state,
+            other => {
+                self.exec_state = other; // drop any unrelated (e.g. Pascal) paused state
+                BasicExecState {
+                    lines: Vec::new(),
+                    line_numbers: HashMap::new(),
+                    pilot_labels: HashMap::new(),
+                    for_stack: Vec::new(),
+                    gosub_stack: Vec::new(),
+                    i: 0,
+                    output: Vec::new(),
+                }
+            }
+        };
+
+        let idx = state.lines.len();
+        if let Some(space_pos) = line.find(' ') {
+            if let Ok(line_num) = line[..space_pos].parse::<u32>() {
+                let command = line[space_pos..].trim().to_string();
+                state.line_numbers.insert(line_num, idx);
+                state.lines.push((line_num, command));
+                self.exec_state = ExecState::Basic(state);
+                return;
+            }
+        }
+        if let Some(label) = line.strip_prefix('*') {
+            state.pilot_labels.insert(label.trim().to_uppercase(), idx);
+        }
+        state.lines.push((idx as u32, line.to_string()));
+        self.exec_state = ExecState::Basic(state);
+    }
+
+    fn push_repl_history(&mut self, line: String) {
+        if self.code_history_index + 1 < self.code_history.len() {
+            self.code_history.truncate(self.code_history_index + 1);
+        }
+        self.code_history.push(line);
+        self.code_history_index = self.code_history.len() - 1;
+    }
+}
+
+/// Classify an `expr::eval_str`/`expr::eval` error string into a diagnostic
+/// kind: "Undefined variable: ..." is its own kind since it's by far the most
+/// common mistake, "Unterminated string: ..." gets its own kind too since
+/// it's a tokenizer-level problem rather than a value one, and everything
+/// else the expression evaluator can fail on (bad numbers, division by zero,
+/// wrong-shaped operands, parse errors) is a type mismatch from the
+/// interpreter's point of view.
+fn classify_expr_error(err: &str) -> ErrorKind {
+    if err.starts_with("Undefined variable") {
+        ErrorKind::UndefinedVariable
+    } else if err.starts_with("Unterminated string") {
+        ErrorKind::UnterminatedString
+    } else {
+        ErrorKind::TypeMismatch
+    }
+}
+
+/// Find the first standalone, case-insensitive occurrence of `keyword` in `text`,
+/// i.e. not glued to surrounding identifier characters (so `TOTAL` doesn't match `TO`).
+fn find_keyword(text: &str, keyword: &str) -> Option<usize> {
+    let upper = text.to_uppercase();
+    let keyword = keyword.to_uppercase();
+    let mut search_from = 0;
+    while let Some(rel_pos) = upper[search_from..].find(&keyword) {
+        let pos = search_from + rel_pos;
+        let before_ok = pos == 0 || !upper.as_bytes()[pos - 1].is_ascii_alphanumeric();
+        let after = pos + keyword.len();
+        let after_ok = after >= upper.len() || !upper.as_bytes()[after].is_ascii_alphanumeric();
+        if before_ok && after_ok {
+            return Some(pos);
+        }
+        search_from = pos + keyword.len();
+    }
+    None
+}
+
+/// Parse a PILOT command prefix: a command letter (T, A, M, J, U, Y, N), an optional
+/// Y/N conditioner, a colon, and the remaining text. Returns `None` for anything else.
+fn parse_pilot_command(command: &str) -> Option<(char, Option<bool>, &str)> {
+    let mut chars = command.char_indices();
+    let (_, first) = chars.next()?;
+    let base = first.to_ascii_uppercase();
+    if !matches!(base, 'T' | 'A' | 'M' | 'J' | 'U' | 'Y' | 'N') {
+        return None;
+    }
+    let (second_idx, second) = chars.next()?;
+    if second == ':' {
+        return Some((base, None, &command[second_idx + 1..]));
+    }
+    if second.to_ascii_uppercase() == 'Y' || second.to_ascii_uppercase() == 'N' {
+        let conditioner = second.to_ascii_uppercase() == 'Y';
+        let (third_idx, third) = chars.next()?;
+        if third == ':' {
+            return Some((base, Some(conditioner), &command[third_idx + 1..]));
+        }
+    }
+    None
+}
+
+/// Split `text` on `sep`, ignoring occurrences inside double-quoted strings.
+fn split_top_level(text: &str, sep: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in text.chars() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+            current.push(c);
+        } else if c == sep && !in_quotes {
+            parts.push(current.clone());
+            current.clear();
+        } else {
+            current.push(c);
+        }
+    }
+    parts.push(current);
+    parts
 }
 
 impl eframe::App for TimeWarpApp {
@@ -1005,6 +1363,18 @@ impl eframe::App for TimeWarpApp {
             self.code_history.push(self.code.clone());
             self.code_history_index = self.code_history.len() - 1;
         }
+
+        // Flycheck: only send a new request when the buffer actually changed
+        // since the last one, then drain whatever the background worker has
+        // finished without blocking this frame.
+        if self.code != self.last_checked_code {
+            self.flycheck.request(self.code.clone(), self.language.clone());
+            self.last_checked_code = self.code.clone();
+        }
+        if let Some(diagnostics) = self.flycheck.poll() {
+            self.live_diagnostics = diagnostics;
+        }
+
         // Light theme for a more educational/clean look
         ctx.set_visuals(egui::Visuals::light());
 
@@ -1189,9 +1559,19 @@ impl eframe::App for TimeWarpApp {
                 // === RUN MENU ===
                 ui.menu_button("‚ñ∂Ô∏è Run", |ui| {
                     if ui.button("üöÄ Run Program").clicked() {
+                        self.exec_state = ExecState::Idle;
+                        self.repl_basic_active = false;
                         self.execute_code();
                         ui.close_menu();
                     }
+                    if ui.button("⏭ Step").clicked() {
+                        self.step();
+                        ui.close_menu();
+                    }
+                    if ui.button("⏩ Continue").clicked() {
+                        self.continue_execution();
+                        ui.close_menu();
+                    }
                     if ui.button("üõë Stop Program").clicked() {
                         self.stop_execution();
                         ui.close_menu();
@@ -1232,6 +1612,8 @@ impl eframe::App for TimeWarpApp {
                         ui.close_menu();
                     }
                     ui.separator();
+                    ui.checkbox(&mut self.consult_stdlib, "Consult TW Prolog standard library (lists + ugraphs)");
+                    ui.separator();
                     if ui.button("üì¶ Plugin Manager").clicked() {
                         self.output = "Plugin Manager not implemented.".to_string();
                         ui.close_menu();
@@ -1295,8 +1677,16 @@ impl eframe::App for TimeWarpApp {
                 ui.separator();
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     if ui.button("Run ‚ñ∂").clicked() {
+                        self.exec_state = ExecState::Idle;
+                        self.repl_basic_active = false;
                         self.execute_code();
                     }
+                    if ui.button("Continue").clicked() {
+                        self.continue_execution();
+                    }
+                    if ui.button("Step").clicked() {
+                        self.step();
+                    }
                 });
             });
         });
@@ -1317,6 +1707,9 @@ impl eframe::App for TimeWarpApp {
                     if ui.selectable_label(self.active_tab == 2, "üê¢ Turtle Graphics").clicked() {
                         self.active_tab = 2;
                     }
+                    if ui.selectable_label(self.active_tab == 3, "⌨ REPL Console").clicked() {
+                        self.active_tab = 3;
+                    }
                 });
 
                 ui.separator();
@@ -1356,13 +1749,36 @@ impl eframe::App for TimeWarpApp {
                         }
 
                         // Code editor with optional line numbers
+                        let language = self.language.clone();
+                        let mut layouter = move |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                            let mut job = highlight::highlight_layout(text, &language, egui::FontId::monospace(14.0));
+                            job.wrap.max_width = wrap_width;
+                            ui.fonts(|fonts| fonts.layout_job(job))
+                        };
+
                         if self.show_line_numbers {
                             let lines: Vec<&str> = self.code.lines().collect();
 
                             egui::ScrollArea::vertical().show(ui, |ui| {
                                 for (i, line) in lines.iter().enumerate() {
                                     ui.horizontal(|ui| {
-                                        ui.label(format!("{:4}: ", i + 1));
+                                        // Flycheck gutter marker: the line number turns red (or
+                                        // yellow for a warning) when that line has a live diagnostic,
+                                        // with the message shown on hover.
+                                        let hit = self.live_diagnostics.iter().find(|d| d.line == i);
+                                        let label = match hit {
+                                            Some(d) => {
+                                                let color = match d.severity {
+                                                    flycheck::Severity::Error => egui::Color32::RED,
+                                                    flycheck::Severity::Warning => egui::Color32::from_rgb(200, 150, 0),
+                                                };
+                                                ui.colored_label(color, format!("{:4}: ", i + 1))
+                                            }
+                                            None => ui.label(format!("{:4}: ", i + 1)),
+                                        };
+                                        if let Some(d) = hit {
+                                            label.on_hover_text(&d.message);
+                                        }
                                         ui.label(*line);
                                     });
                                 }
@@ -1371,11 +1787,25 @@ impl eframe::App for TimeWarpApp {
                             // Separate editor for modifications when line numbers are shown
                             ui.separator();
                             ui.label("Edit below:");
-                            if ui.text_edit_multiline(&mut self.code).changed() {
+                            if ui
+                                .add(egui::TextEdit::multiline(&mut self.code).font(egui::FontId::monospace(14.0)).layouter(&mut layouter))
+                                .changed()
+                            {
                                 // Code was edited, could refresh line numbers display
                             }
                         } else {
-                            ui.text_edit_multiline(&mut self.code);
+                            ui.add(egui::TextEdit::multiline(&mut self.code).font(egui::FontId::monospace(14.0)).layouter(&mut layouter));
+                        }
+
+                        if !self.live_diagnostics.is_empty() {
+                            ui.separator();
+                            for d in &self.live_diagnostics {
+                                let color = match d.severity {
+                                    flycheck::Severity::Error => egui::Color32::RED,
+                                    flycheck::Severity::Warning => egui::Color32::from_rgb(200, 150, 0),
+                                };
+                                ui.colored_label(color, format!("line {}: {}", d.line + 1, d.message));
+                            }
                         }
                     }
                     1 => { // Output Tab
@@ -1383,6 +1813,13 @@ impl eframe::App for TimeWarpApp {
                         ui.add_space(4.0);
                         egui::ScrollArea::vertical().show(ui, |ui| {
                             ui.label(&self.output);
+                            for error in &self.diagnostics {
+                                ui.add_space(4.0);
+                                ui.colored_label(
+                                    egui::Color32::RED,
+                                    format!("error[{}]: {} (line {})", error.kind.label(), error.message, error.line + 1),
+                                );
+                            }
                         });
 
                         // Input field when waiting for user input
@@ -1397,6 +1834,19 @@ impl eframe::App for TimeWarpApp {
                                 }
                             });
                         }
+
+                        // Variable table, for inspecting state between Step clicks.
+                        ui.separator();
+                        ui.collapsing("Variables", |ui| {
+                            let mut names: Vec<&String> = self.variables.keys().collect();
+                            names.sort();
+                            if names.is_empty() {
+                                ui.label("(none defined)");
+                            }
+                            for name in names {
+                                ui.label(format!("{} = {}", name, self.variables[name].display()));
+                            }
+                        });
                     }
                     2 => { // Turtle Graphics Tab
                         ui.heading("Turtle Graphics");
@@ -1488,6 +1938,75 @@ impl eframe::App for TimeWarpApp {
                             if self.turtle_state.pen_down { "down" } else { "up" }
                         ));
                     }
+                    3 => { // REPL Console Tab
+                        ui.heading("REPL Console");
+                        ui.label(format!("Statements run in {} mode; variables persist between lines.", self.language));
+                        ui.add_space(4.0);
+                        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                            ui.label(&self.repl_log);
+                        });
+                        ui.separator();
+
+                        let suggestions = repl::complete(&self.repl_input, &self.language, &self.variables);
+                        if !suggestions.is_empty() {
+                            ui.label(format!("Suggestions: {}", suggestions.join(", ")));
+                        }
+
+                        if self.reverse_search_active {
+                            ui.horizontal(|ui| {
+                                ui.label("(reverse-search)`");
+                                let response = ui.text_edit_singleline(&mut self.reverse_search_query);
+                                response.request_focus();
+                                ui.label("`:");
+                                if let Some(idx) =
+                                    repl::reverse_search(&self.reverse_search_query, &self.code_history, self.code_history_index)
+                                {
+                                    self.repl_input = self.code_history[idx].clone();
+                                    ui.label(&self.repl_input);
+                                    if ui.input(|i| i.key_pressed(egui::Key::R) && i.modifiers.ctrl) {
+                                        self.code_history_index = idx;
+                                    }
+                                }
+                                if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                                    self.reverse_search_active = false;
+                                    self.submit_repl_line();
+                                } else if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                                    self.reverse_search_active = false;
+                                    self.reverse_search_query.clear();
+                                }
+                            });
+                        } else {
+                            ui.horizontal(|ui| {
+                                let response = ui.text_edit_singleline(&mut self.repl_input);
+                                if response.has_focus() {
+                                    if ui.input(|i| i.key_pressed(egui::Key::R) && i.modifiers.ctrl) {
+                                        self.reverse_search_active = true;
+                                        self.reverse_search_query.clear();
+                                    }
+                                    if ui.input(|i| i.key_pressed(egui::Key::ArrowUp)) && self.code_history_index > 0 {
+                                        self.code_history_index -= 1;
+                                        self.repl_input = self.code_history[self.code_history_index].clone();
+                                    }
+                                    if ui.input(|i| i.key_pressed(egui::Key::ArrowDown))
+                                        && self.code_history_index + 1 < self.code_history.len()
+                                    {
+                                        self.code_history_index += 1;
+                                        self.repl_input = self.code_history[self.code_history_index].clone();
+                                    }
+                                    if ui.input(|i| i.key_pressed(egui::Key::Tab)) {
+                                        if let Some(first) = suggestions.first() {
+                                            self.repl_input = first.clone();
+                                        }
+                                    }
+                                }
+                                let submit = ui.button("Run").clicked()
+                                    || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)));
+                                if submit {
+                                    self.submit_repl_line();
+                                }
+                            });
+                        }
+                    }
                     _ => {}
                 }
             });
@@ -1496,6 +2015,26 @@ impl eframe::App for TimeWarpApp {
 }
 
 fn main() -> eframe::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(run_args) = cli::parse_run_args(&args) {
+        if let Err(err) = cli::run_headless(run_args) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(language) = cli::parse_repl_args(&args) {
+        let mut app = TimeWarpApp::default();
+        if let Some(language) = language {
+            app.language = language;
+        }
+        if let Err(err) = repl::run_stdin_repl(&mut app) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let options = eframe::NativeOptions::default();
     eframe::run_native(
         "Time Warp IDE",
@@ -1503,3 +2042,26 @@ fn main() -> eframe::Result<()> {
         Box::new(|_cc| Box::new(TimeWarpApp::default())),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A FOR/NEXT loop split across three separate REPL submissions should
+    /// run to completion, not "NEXT without a matching FOR": the for_stack
+    /// built by `FOR` on one line must survive to be popped by `NEXT` on a
+    /// later one.
+    #[test]
+    fn repl_for_next_spans_submitted_lines() {
+        let mut app = TimeWarpApp::default();
+        app.language = "TW BASIC".to_string();
+
+        for line in ["10 FOR I = 1 TO 3", "20 PRINT I", "30 NEXT I"] {
+            app.repl_input = line.to_string();
+            app.submit_repl_line();
+        }
+
+        assert!(app.diagnostics.is_empty(), "unexpected diagnostics: {:?}", app.diagnostics);
+        assert_eq!(app.variables.get("I"), Some(&Value::Number(3.0)));
+    }
+}
@@ -0,0 +1,124 @@
+//! REPL subsystem shared by the egui console pane and the headless stdin prompt.
+//!
+//! Completion is language-aware (keywords plus whatever variables are
+//! currently defined); history reuses `TimeWarpApp::code_history` so the same
+//! ring backs undo/redo in the editor and up/down recall in the REPL.
+
+use crate::expr::VarStore;
+use crate::TimeWarpApp;
+use std::io::{self, BufRead, Write};
+
+pub(crate) const BASIC_KEYWORDS: &[&str] = &[
+    "PRINT", "LET", "INPUT", "IF", "THEN", "GOTO", "GOSUB", "RETURN", "FOR", "TO", "STEP", "NEXT",
+    "WHILE", "WEND", "CLS", "COLOR", "BEEP", "SOUND", "REM",
+];
+pub(crate) const LOGO_KEYWORDS: &[&str] = &[
+    "FORWARD", "FD", "BACKWARD", "BK", "RIGHT", "RT", "LEFT", "LT", "PENUP", "PENDOWN", "HOME",
+    "CLEARSCREEN", "CS", "SETPENCOLOR", "MAKE", "REPEAT",
+];
+pub(crate) const PILOT_KEYWORDS: &[&str] = &["T:", "A:", "M:", "J:", "U:", "Y:", "N:"];
+pub(crate) const PASCAL_KEYWORDS: &[&str] = &[
+    "program", "begin", "end", "var", "const", "type", "writeln", "write", "readln", "if", "then",
+    "else", "for", "while", "repeat", "until", "case", "procedure", "function",
+];
+pub(crate) const PROLOG_KEYWORDS: &[&str] = &[
+    "is", "mod", "not", "true", "fail", "write", "nl", "domains", "predicates", "clauses", "goal",
+];
+
+/// The keyword set for a given editor language, dialect-aware: TW BASIC also
+/// pulls in its Logo turtle commands and PILOT CAI commands, since all three
+/// share the same editor tab.
+pub(crate) fn keywords_for(language: &str) -> Vec<&'static str> {
+    match language {
+        "TW Pascal" => PASCAL_KEYWORDS.to_vec(),
+        "TW Prolog" => PROLOG_KEYWORDS.to_vec(),
+        _ => BASIC_KEYWORDS
+            .iter()
+            .chain(LOGO_KEYWORDS.iter())
+            .chain(PILOT_KEYWORDS.iter())
+            .copied()
+            .collect(),
+    }
+}
+
+/// Suggest completions for `prefix`: language keywords and currently defined
+/// variable names that start with it (case-insensitively), merged into one
+/// sorted, deduplicated list.
+pub(crate) fn complete(prefix: &str, language: &str, variables: &VarStore) -> Vec<String> {
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+    let upper_prefix = prefix.to_uppercase();
+
+    let mut matches: Vec<String> = keywords_for(language)
+        .iter()
+        .filter(|kw| kw.to_uppercase().starts_with(&upper_prefix))
+        .map(|kw| kw.to_string())
+        .collect();
+
+    matches.extend(
+        variables
+            .keys()
+            .filter(|name| name.to_uppercase().starts_with(&upper_prefix))
+            .cloned(),
+    );
+
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+/// Bash-style `Ctrl-R` reverse history search: scan `history` from `start`
+/// backwards (exclusive) for the most recent entry containing `query` as a
+/// case-insensitive substring. Repeated calls with a smaller `start` walk
+/// further back through older matches.
+pub(crate) fn reverse_search(query: &str, history: &[String], start: usize) -> Option<usize> {
+    if query.is_empty() {
+        return None;
+    }
+    let upper_query = query.to_uppercase();
+    history[..start.min(history.len())]
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(_, entry)| entry.to_uppercase().contains(&upper_query))
+        .map(|(idx, _)| idx)
+}
+
+/// Run the REPL as a blocking stdin/stdout prompt: read one line at a time,
+/// execute it against the persistent interpreter state, and print the result,
+/// until EOF or `exit`/`quit`.
+///
+/// Scope decision: this is a bare `read_line` loop with no line editing at
+/// all - no history recall, no TAB completion, no `reverse_search` above.
+/// Those all depend on an interactive terminal (raw mode, cursor control)
+/// that a piped headless stdin doesn't give us; the egui Console tab is
+/// where history, completion, and reverse search actually live. A real
+/// terminal UI here would need something like a `readline`/raw-mode crate,
+/// which is more than this headless entry point needs.
+pub(crate) fn run_stdin_repl(app: &mut TimeWarpApp) -> io::Result<()> {
+    let stdin = io::stdin();
+    loop {
+        print!("{}> ", app.language);
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break; // EOF
+        }
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.eq_ignore_ascii_case("exit") || line.eq_ignore_ascii_case("quit") {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        app.repl_input = line.to_string();
+        app.submit_repl_line();
+        print!("{}", app.repl_log);
+        app.repl_log.clear();
+        io::stdout().flush()?;
+    }
+    Ok(())
+}